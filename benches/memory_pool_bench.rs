@@ -28,7 +28,7 @@ fn unconstrained_memory_pool_bench(b: &mut Bencher) {
 
     b.iter(|| {
         for _ in 0..10 {
-            black_box(mem.get());
+            black_box(mem.get().unwrap());
         }
     })
 }
@@ -2,6 +2,7 @@ use std::sync::Arc;
 use std::sync::mpsc;
 use std::ops::Deref;
 use std::ops::DerefMut;
+use std::fmt;
 
 /// This trait is required to use
 /// a type in the memory pool
@@ -32,23 +33,42 @@ pub trait Recycle {
     fn recycle(&mut self);
 }
 
+/// Shared closure used to reset a slot before it re-enters the pool, in
+/// place of a `Recycle` impl
+pub type RecyclerFn<T> = Arc<Fn(&mut T)>;
+
 /// A smart pointer that returns memory to
 /// its owner when it is dropped
-#[derive(Debug)]
-pub struct ArcRecycled<T: Recycle> {
+pub struct ArcRecycled<T> {
     /// Data content
     content: Option<Arc<T>>,
 
     /// Owner's reception channel
     owner: mpsc::Sender<Option<T>>,
+
+    /// Closure used to reset the slot before it is sent back, if any
+    recycler: Option<RecyclerFn<T>>,
 }
 
 impl<T: Recycle> ArcRecycled<T> {
-    /// Constructor function, takes the memory slot and the channel to the pool
+    /// Constructor function, takes the memory slot and the channel to the pool.
+    /// The slot is reset via `Recycle::recycle` before being sent back;
+    /// use `with_recycler` to supply a custom reset closure instead
     pub fn new(data: T, owner_channel: mpsc::Sender<Option<T>>) -> ArcRecycled<T> {
+        let recycler: RecyclerFn<T> = Arc::new(|mem_slot: &mut T| mem_slot.recycle());
+        ArcRecycled::with_recycler(data, owner_channel, Some(recycler))
+    }
+}
+
+impl<T> ArcRecycled<T> {
+    /// Constructor function, takes the memory slot, the channel to the
+    /// pool, and an optional closure used to reset the slot before it is
+    /// sent back. This does not require `T: Recycle`
+    pub(crate) fn with_recycler(data: T, owner_channel: mpsc::Sender<Option<T>>, recycler: Option<RecyclerFn<T>>) -> ArcRecycled<T> {
         ArcRecycled {
             content: Some(Arc::new(data)),
             owner: owner_channel,
+            recycler,
         }
     }
 
@@ -65,20 +85,39 @@ impl<T: Recycle> ArcRecycled<T> {
         let arc = self.content.as_mut().expect("Missing content");
         Arc::get_mut(arc)
     }
+
+    /// Permanently takes the inner value out of the pool, if this is the
+    /// sole owner. Returns None, leaving the value untouched, if there
+    /// are other owners.
+    ///
+    /// The slot is not returned to the pool: dropping self afterwards
+    /// sends a tombstone instead, so the pool's outstanding count stays
+    /// accurate without the value itself being recycled
+    pub fn detach(mut self) -> Option<T> {
+        let value = self.content.take().expect("Missing content");
+        match Arc::try_unwrap(value) {
+            Ok(mem_slot) => Some(mem_slot),
+            Err(arc) => {
+                self.content = Some(arc);
+                None
+            }
+        }
+    }
 }
 
-impl<T: Recycle> Clone for ArcRecycled<T> {
+impl<T> Clone for ArcRecycled<T> {
     /// Normal clone, but it is better
     /// explicited here.
     fn clone(&self) -> ArcRecycled<T> {
         ArcRecycled {
             content: self.content.clone(),
             owner: self.owner.clone(),
+            recycler: self.recycler.clone(),
         }
     }
 }
 
-impl<T: Recycle> Drop for ArcRecycled<T> {
+impl<T> Drop for ArcRecycled<T> {
     /// If strong_count is at 1, return data to owner
     fn drop(&mut self) {
         match self.content.take() {
@@ -89,7 +128,9 @@ impl<T: Recycle> Drop for ArcRecycled<T> {
                     /// If this fails, we have lost our pool. The mem
                     /// slot will be simply dropped
                     Ok(mut mem_slot) => {
-                        mem_slot.recycle();
+                        if let Some(ref recycler) = self.recycler {
+                            recycler(&mut mem_slot);
+                        }
                         let _ = self.owner.send(Some(mem_slot));
                     }
                     /// If not unwrapped, you are not truly the last owner
@@ -110,7 +151,7 @@ impl<T: Recycle> Drop for ArcRecycled<T> {
     }
 }
 
-impl<T: Recycle> Deref for ArcRecycled<T> {
+impl<T> Deref for ArcRecycled<T> {
     type Target = T;
     /// Give a reference directly to the
     /// innermost content
@@ -119,7 +160,7 @@ impl<T: Recycle> Deref for ArcRecycled<T> {
     }
 }
 
-impl<T: Recycle> DerefMut for ArcRecycled<T> {
+impl<T> DerefMut for ArcRecycled<T> {
     /// Give a mutable reference directly to the
     /// innermost content
     ///
@@ -136,6 +177,16 @@ impl<T: Recycle> DerefMut for ArcRecycled<T> {
     }
 }
 
+impl<T: fmt::Debug> fmt::Debug for ArcRecycled<T> {
+    /// The recycler closure, if any, has no useful debug representation
+    /// and is omitted
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ArcRecycled")
+            .field("content", &self.content)
+            .finish()
+    }
+}
+
 impl<T> Recycle for Vec<T> {
     fn recycle(&mut self) {
         self.clear();
@@ -209,4 +260,56 @@ mod tests {
         assert_eq!(rec.len(), 10);
         assert_eq!(rec.capacity(), 50);
     }
+
+    #[test]
+    fn detach_sole_owner_test() {
+        let (tx, rx) = mpsc::channel();
+        let mut rec = ArcRecycled::new(Vec::<f64>::with_capacity(50), tx);
+        rec.push(5.0);
+
+        let value = rec.detach().unwrap();
+        assert_eq!(value.len(), 1);
+
+        /// A tombstone is sent instead of the slot itself
+        assert_eq!(rx.recv().unwrap(), None);
+    }
+
+    #[test]
+    fn detach_shared_owner_test() {
+        let (tx, rx) = mpsc::channel();
+        let rec = ArcRecycled::new(Vec::<f64>::with_capacity(50), tx);
+        let _rec2 = rec.clone();
+
+        assert!(rec.detach().is_none());
+
+        /// `_rec2` still owns the slot, nothing sent back yet
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn custom_recycler_test() {
+        let (tx, rx) = mpsc::channel();
+        {
+            let mut rec = ArcRecycled::with_recycler(
+                String::from("hello"),
+                tx,
+                Some(Arc::new(|s: &mut String| s.clear())),
+            );
+            rec.push_str(", world");
+        }
+
+        let new_val = rx.recv().unwrap().unwrap();
+        assert_eq!(new_val, "");
+    }
+
+    #[test]
+    fn no_recycler_test() {
+        let (tx, rx) = mpsc::channel();
+        {
+            let _rec = ArcRecycled::with_recycler(String::from("hello"), tx, None);
+        }
+
+        let new_val = rx.recv().unwrap().unwrap();
+        assert_eq!(new_val, "hello");
+    }
 }
@@ -0,0 +1,73 @@
+use memory_pool::{
+    MemoryPool,
+    CreateFn,
+};
+
+/// Boxed closure used to reset a slot before it re-enters the pool, in
+/// place of a `Recycle` impl. Boxed rather than `Arc`'d like
+/// `arc_recycled::RecyclerFn`, since a builder owns its recycler outright
+/// and only ever needs to move it once, into the `MemoryPool` it builds
+pub type BoxedRecycleFn<T> = Box<Fn(&mut T)>;
+
+/// Builds a `MemoryPool<T>` from a creator closure and an optional
+/// recycler closure, without requiring `T` to implement `Recycle`. This
+/// lets foreign types (e.g. `String`, `HashMap`) be pooled with custom
+/// reset logic
+#[allow(missing_debug_implementations)]
+pub struct MemoryPoolBuilder<T> {
+    creator: CreateFn<T>,
+    recycle: Option<BoxedRecycleFn<T>>,
+}
+
+impl<T: 'static> MemoryPoolBuilder<T> {
+    /// Constructor, takes the creator closure used to initialize mem slots
+    pub fn new(creator: CreateFn<T>) -> MemoryPoolBuilder<T> {
+        MemoryPoolBuilder {
+            creator,
+            recycle: None,
+        }
+    }
+
+    /// Sets the closure called on a slot before it re-enters the pool.
+    /// Without one, slots are handed back untouched
+    pub fn recycle_with(mut self, recycle: BoxedRecycleFn<T>) -> MemoryPoolBuilder<T> {
+        self.recycle = Some(recycle);
+        self
+    }
+
+    /// Builds the pool, must take initial size and maximum size
+    /// # Panics
+    /// This function will panic if size > max
+    pub fn build(self, size: usize, max: usize) -> MemoryPool<T, ()> {
+        MemoryPool::from_builder(size, max, self.creator, self.recycle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builder_without_recycle_impl_test() {
+        let pool = MemoryPoolBuilder::new(Box::new(|| String::with_capacity(16)))
+            .build(2, 4);
+
+        let mut slot = pool.get().unwrap();
+        slot.push_str("hello");
+    }
+
+    #[test]
+    fn builder_with_custom_recycler_test() {
+        let pool = MemoryPoolBuilder::new(Box::new(|| String::with_capacity(16)))
+            .recycle_with(Box::new(|s: &mut String| s.clear()))
+            .build(1, 1);
+
+        {
+            let mut slot = pool.get().unwrap();
+            slot.push_str("hello");
+        }
+
+        let recycled = pool.get().unwrap();
+        assert_eq!(recycled.as_str(), "");
+    }
+}
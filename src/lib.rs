@@ -24,14 +24,49 @@ pub mod arc_recycled;
 /// Definition of the pool structure
 pub mod memory_pool;
 
+/// Definition of the thread-local batching handle
+pub mod local_handle;
+
+/// Definition of the size-classed pool
+pub mod sized_memory_pool;
+
+/// Definition of the pool builder
+pub mod builder;
+
 /// Memory pool
 pub use memory_pool::MemoryPool;
 
 /// Initialization function
 pub use memory_pool::CreateFn;
 
+/// Fallible initialization function
+pub use memory_pool::TryCreateFn;
+
+/// Error returned when a memory slot could not be handed out
+pub use memory_pool::PoolError;
+
+/// Thread-local batching handle
+pub use local_handle::LocalHandle;
+
+/// Size-classed pool
+pub use sized_memory_pool::SizedMemoryPool;
+
+/// Initialization function for a size-classed pool
+pub use sized_memory_pool::SizedCreateFn;
+
 /// Smart pointer
 pub use arc_recycled::ArcRecycled;
 
 /// Trait to use object in mem-pool
 pub use arc_recycled::Recycle;
+
+/// Shared (`Arc`'d) recycler closure threaded through every `ArcRecycled`
+/// handed out by a pool
+pub use arc_recycled::RecyclerFn;
+
+/// Builder for pools over types that do not implement `Recycle`
+pub use builder::MemoryPoolBuilder;
+
+/// Owned (`Box`'d) recycler closure handed to a `MemoryPoolBuilder`
+/// before it is moved, once, into the `MemoryPool` it builds
+pub use builder::BoxedRecycleFn;
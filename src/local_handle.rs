@@ -0,0 +1,192 @@
+use std::sync::mpsc;
+use arc_recycled::ArcRecycled;
+use memory_pool::{
+    MemoryPool,
+    PoolError,
+};
+
+/// A thread-local front-end cache over a `MemoryPool`.
+///
+/// Instead of paying one channel operation and one atomic per `get`,
+/// a `LocalHandle` stages up to `batch` slots drained from the shared
+/// pool in one go, then hands them out locally without receiving from
+/// the channel or touching the pool's `size` atomic until the staging
+/// buffer runs dry. It is meant to
+/// be created once per thread (e.g. via `thread_local!`) and reused.
+#[allow(missing_debug_implementations)]
+pub struct LocalHandle<'a, T: 'a, E: 'a = ()> {
+    pool: &'a MemoryPool<T, E>,
+    batch: usize,
+    staged: Vec<T>,
+}
+
+impl<'a, T, E> LocalHandle<'a, T, E> {
+    /// Constructor, not meant to be called directly.
+    /// Use `MemoryPool::local_handle` instead
+    pub(crate) fn new(pool: &'a MemoryPool<T, E>, batch: usize) -> LocalHandle<'a, T, E> {
+        LocalHandle {
+            pool,
+            batch,
+            staged: Vec::with_capacity(batch),
+        }
+    }
+
+    /// Number of slots currently staged locally, never more than `batch`
+    #[cfg(test)]
+    fn staged_len(&self) -> usize {
+        self.staged.len()
+    }
+
+    /// This function returns a memory slot, refilling the local
+    /// staging buffer from the shared pool if it has run dry
+    /// # Errors
+    /// Returns `PoolError::Exhausted` or `PoolError::CreationFailed`
+    /// under the same conditions as `MemoryPool::get`
+    pub fn get(&mut self) -> Result<ArcRecycled<T>, PoolError<E>> {
+        if self.staged.is_empty() {
+            self.refill()?;
+        }
+
+        let mem_slot = self.staged.pop().expect("refill must stage at least one slot");
+        Ok(ArcRecycled::with_recycler(mem_slot, self.pool.sender(), self.pool.recycler()))
+    }
+
+    /// Drains up to `batch` slots from the pool's shared channel in one
+    /// tight `try_recv` loop, creating new ones up to `max` only once
+    /// the channel itself is dry
+    fn refill(&mut self) -> Result<(), PoolError<E>> {
+        while self.staged.len() < self.batch {
+            match self.pool.try_recv() {
+                /// Got a slot straight from the shared channel
+                Ok(Some(mem_slot)) => {
+                    self.staged.push(mem_slot);
+                }
+
+                /// A tombstone left behind by a detached slot, account for it and keep draining
+                Ok(None) => {
+                    self.pool.release_stale();
+                }
+
+                /// Channel is dry, try to create a new slot instead
+                Err(mpsc::TryRecvError::Empty) => {
+                    match self.pool.try_create_one() {
+                        Some(Ok(mem_slot)) => self.staged.push(mem_slot),
+                        Some(Err(e)) => return Err(PoolError::CreationFailed(e)),
+                        None => {
+                            if self.staged.is_empty() {
+                                return Err(PoolError::Exhausted);
+                            }
+                            break;
+                        }
+                    }
+                }
+
+                /// Unreachable
+                Err(_) => {
+                    unreachable!("If the memory pool is alive, the channel cannot be disconnected")
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a, T, E> Drop for LocalHandle<'a, T, E> {
+    /// Any slots still staged when the handle is dropped are pushed
+    /// back through the shared `sender` so they are not leaked. The
+    /// pool's `size` atomic already accounts for them: a slot drained
+    /// from the channel was counted before it was staged, and a slot
+    /// created by `refill` was counted when it was created, so moving
+    /// them back to the channel needs no change to `size`
+    fn drop(&mut self) {
+        let sender = self.pool.sender();
+        for mem_slot in self.staged.drain(..) {
+            let _ = sender.send(Some(mem_slot));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use memory_pool::{
+        MemoryPool,
+        PoolError,
+    };
+
+    #[test]
+    fn drain_then_refill_test() {
+        let mem = MemoryPool::create_with(4, 4, Box::new(|| { Vec::<f64>::with_capacity(20) }));
+        let mut handle = mem.local_handle(2);
+
+        for _ in 0..4 {
+            assert!(handle.get().is_ok());
+            assert!(handle.staged_len() <= 2);
+        }
+    }
+
+    #[test]
+    fn drop_returns_staged_slots_to_sender_test() {
+        let mem = MemoryPool::create_with(2, 2, Box::new(|| { Vec::<f64>::with_capacity(20) }));
+        let _v;
+        {
+            let mut handle = mem.local_handle(2);
+            _v = handle.get().unwrap();
+            assert_eq!(handle.staged_len(), 1);
+            // `handle` is dropped here, its one remaining staged slot
+            // must be pushed back through the pool's shared channel
+        }
+
+        /// The pool is already at `max`, so this can only succeed if the
+        /// slot the handle staged but never handed out was given back
+        assert!(mem.get().is_ok());
+    }
+
+    #[test]
+    fn tombstone_handling_in_refill_test() {
+        let mem = MemoryPool::create_with(2, 2, Box::new(|| { Vec::<f64>::with_capacity(20) }));
+        let v1 = mem.get().unwrap();
+
+        /// Leaves a tombstone behind in the shared channel
+        assert!(v1.detach().is_some());
+
+        let mut handle = mem.local_handle(2);
+        /// `refill` must skip the tombstone, reconcile `size` for it,
+        /// and create a fresh slot to take its place instead of
+        /// returning a stale or short batch
+        assert!(handle.get().is_ok());
+    }
+
+    #[test]
+    fn size_reconciliation_after_tombstone_test() {
+        let mem = MemoryPool::create_with(2, 2, Box::new(|| { Vec::<f64>::with_capacity(20) }));
+        let v1 = mem.get().unwrap();
+        assert!(v1.detach().is_some());
+
+        let mut handle = mem.local_handle(2);
+        /// Refill must reconcile `size` for the tombstone left by `detach`
+        /// and recreate a slot in its place
+        let _v2 = handle.get().unwrap();
+        drop(handle);
+
+        /// The other slot was only ever staged, never handed out, so it
+        /// was pushed back through the channel when `handle` was dropped
+        let _v3 = mem.get().unwrap();
+
+        /// `size` is exactly `max` again (the recreated slot plus the one
+        /// just reclaimed above), both still checked out, so a further
+        /// get must be refused rather than silently creating past `max`
+        match mem.get() {
+            Err(PoolError::Exhausted) => {}
+            _ => panic!("Expected PoolError::Exhausted"),
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "batch must be greater than 0")]
+    fn zero_batch_panics_test() {
+        let mem = MemoryPool::create_with(1, 1, Box::new(|| { Vec::<f64>::with_capacity(20) }));
+        let _handle = mem.local_handle(0);
+    }
+}
+
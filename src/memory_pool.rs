@@ -1,3 +1,4 @@
+use std::sync::Arc;
 use std::sync::mpsc;
 use std::sync::atomic::{
     AtomicUsize,
@@ -6,52 +7,74 @@ use std::sync::atomic::{
 use arc_recycled::{
     ArcRecycled,
     Recycle,
+    RecyclerFn,
 };
+use local_handle::LocalHandle;
+use builder::BoxedRecycleFn;
 
 /// Boxed closure that creates objects of type T
 pub type CreateFn<T> = Box<Fn() -> T>;
 
+/// Boxed closure that fallibly creates objects of type T,
+/// for example a slot that mmaps a buffer and may fail to do so
+pub type TryCreateFn<T, E> = Box<Fn() -> Result<T, E>>;
+
+/// Error returned when a memory slot could not be handed out
+#[derive(Debug)]
+pub enum PoolError<E> {
+    /// The pool already holds `max` slots and none are free
+    Exhausted,
+    /// The creator closure failed while building a fresh slot
+    CreationFailed(E),
+}
+
 /// Memory pool structure
 #[allow(missing_debug_implementations)]
-pub struct MemoryPool<T> {
+pub struct MemoryPool<T, E = ()> {
     size: AtomicUsize,
     max: usize,
     receiver: mpsc::Receiver<Option<T>>,
     sender: mpsc::Sender<Option<T>>,
-    creator: CreateFn<T>,
+    creator: TryCreateFn<T, E>,
+    recycler: Option<RecyclerFn<T>>,
 }
 
-impl<T: Recycle> MemoryPool<T> {
-    /// Constructor, must take intial size and maximum size.
-    /// The creator closure is used to initialize the mem slots
-    /// # Panics
-    /// This function will panic if size > max
-    pub fn create_with(size: usize, max: usize, creator: CreateFn<T>) -> MemoryPool<T> {
+impl<T, E> MemoryPool<T, E> {
+    /// Shared construction logic: builds the initial `size` slots
+    /// through the fallible creator, threading the given recycler
+    /// (if any) into every slot handed out from here on
+    fn build(size: usize, max: usize, creator: TryCreateFn<T, E>, recycler: Option<RecyclerFn<T>>) -> Result<MemoryPool<T, E>, PoolError<E>> {
         assert!(size <= max);
         let (tx, rx) = mpsc::channel();
         for _ in 0..size {
-            tx.send(Some(creator())).unwrap()
+            match creator() {
+                Ok(mem_slot) => tx.send(Some(mem_slot)).unwrap(),
+                Err(e) => return Err(PoolError::CreationFailed(e)),
+            }
         }
 
-        MemoryPool {
+        Ok(MemoryPool {
             size: AtomicUsize::new(size),
             max,
             receiver: rx,
             sender: tx,
             creator,
-        }
+            recycler,
+        })
     }
 
     /// This function returns a memory slot from the memory pool
-    /// # Panics
-    /// This function will panic if it needs to allocate more than max
-    pub fn get(&self) -> ArcRecycled<T> {
+    /// # Errors
+    /// Returns `PoolError::Exhausted` if the pool already holds `max` slots,
+    /// or `PoolError::CreationFailed` if a fresh slot needed to be created
+    /// and the creator closure failed
+    pub fn get(&self) -> Result<ArcRecycled<T>, PoolError<E>> {
         loop {
             /// Try to get a mem_slot
             match self.receiver.try_recv() {
                 /// If got one wrap and return it
                 Ok(Some(mem_slot)) => {
-                    return ArcRecycled::new(mem_slot, self.sender.clone());
+                    return Ok(ArcRecycled::with_recycler(mem_slot, self.sender.clone(), self.recycler.clone()));
                 }
 
                 /// If got None, keep trying
@@ -60,13 +83,18 @@ impl<T: Recycle> MemoryPool<T> {
                 }
 
                 /// If channel is empty try to create a new memory slot
-                /// If we have place this works, if not, it panics!
+                /// If we have place this works, if not, we are exhausted
                 Err(mpsc::TryRecvError::Empty) => {
-                    if self.size.fetch_add(1, Ordering::Relaxed) < self.max {
-                        return ArcRecycled::new((self.creator)(), self.sender.clone());
-                    }
-                    else {
-                        panic!("Exceeded memory pool limit");
+                    match self.try_create_one() {
+                        Some(Ok(mem_slot)) => {
+                            return Ok(ArcRecycled::with_recycler(mem_slot, self.sender.clone(), self.recycler.clone()));
+                        }
+                        Some(Err(e)) => {
+                            return Err(PoolError::CreationFailed(e));
+                        }
+                        None => {
+                            return Err(PoolError::Exhausted);
+                        }
                     }
                 }
 
@@ -79,38 +107,122 @@ impl<T: Recycle> MemoryPool<T> {
     }
 
     /// This function returns a memory slot from the memory pool
-    /// if size does not exceed max. returns None otherwise
+    /// if size does not exceed max. returns None otherwise,
+    /// discarding the creator's error if creation also failed
     pub fn try_get(&self) -> Option<ArcRecycled<T>> {
-        loop {
-            /// Try to get a mem_slot
-            match self.receiver.try_recv() {
-                /// If got one wrap and return it
-                Ok(Some(mem_slot)) => {
-                    return Some(ArcRecycled::new(mem_slot, self.sender.clone()));
-                }
+        self.get().ok()
+    }
 
-                /// If got None, keep trying
-                Ok(None) => {
-                    self.size.fetch_sub(1, Ordering::Relaxed);
-                }
+    /// Recycles and hands an externally created object to the pool,
+    /// incrementing `size`. This supports seeding the pool with
+    /// pre-warmed objects, or giving back a value previously taken out
+    /// via `ArcRecycled::detach`
+    /// # Errors
+    /// Returns the value back, unchanged, if inserting it would exceed `max`
+    pub fn attach(&self, mut value: T) -> Result<(), T> {
+        if self.size.fetch_add(1, Ordering::Relaxed) < self.max {
+            if let Some(ref recycler) = self.recycler {
+                recycler(&mut value);
+            }
+            let _ = self.sender.send(Some(value));
+            Ok(())
+        }
+        else {
+            self.size.fetch_sub(1, Ordering::Relaxed);
+            Err(value)
+        }
+    }
 
-                /// If channel is empty try to create a new memory slot
-                /// If we have place this works, if not, it panics!
-                Err(mpsc::TryRecvError::Empty) => {
-                    if self.size.fetch_add(1, Ordering::Relaxed) < self.max {
-                        return Some(ArcRecycled::new((self.creator)(), self.sender.clone()));
-                    }
-                    else {
-                        return None;
-                    }
-                }
+    /// Returns a thread-local batching handle over this pool. The handle
+    /// stages up to `batch` slots at a time, amortizing the shared
+    /// channel and `size` atomic traffic across every `batch` calls to
+    /// `LocalHandle::get` instead of paying it on every single one
+    /// # Panics
+    /// This function will panic if `batch` is 0, since a handle could
+    /// then never stage a slot to hand out
+    pub fn local_handle(&self, batch: usize) -> LocalHandle<'_, T, E> {
+        assert!(batch > 0, "batch must be greater than 0");
+        LocalHandle::new(self, batch)
+    }
 
-                /// Unreachable
-                Err(_) => {
-                    unreachable!("If the memory pool is alive, the channel cannot be disconnected")
+    /// Clones a handle to the pool's reception channel
+    pub(crate) fn sender(&self) -> mpsc::Sender<Option<T>> {
+        self.sender.clone()
+    }
+
+    /// Attempts to receive a single slot from the pool's channel
+    /// without blocking
+    pub(crate) fn try_recv(&self) -> Result<Option<T>, mpsc::TryRecvError> {
+        self.receiver.try_recv()
+    }
+
+    /// Reconciles the `size` atomic for a tombstone received from the channel
+    pub(crate) fn release_stale(&self) {
+        self.size.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// Clones the recycler threaded into every slot handed out by this pool
+    pub(crate) fn recycler(&self) -> Option<RecyclerFn<T>> {
+        self.recycler.clone()
+    }
+
+    /// Attempts to create a single new slot if doing so would not exceed
+    /// `max`. Returns `None` if the pool is already at capacity
+    pub(crate) fn try_create_one(&self) -> Option<Result<T, E>> {
+        if self.size.fetch_add(1, Ordering::Relaxed) < self.max {
+            match (self.creator)() {
+                Ok(mem_slot) => Some(Ok(mem_slot)),
+                Err(e) => {
+                    self.size.fetch_sub(1, Ordering::Relaxed);
+                    Some(Err(e))
                 }
             }
         }
+        else {
+            self.size.fetch_sub(1, Ordering::Relaxed);
+            None
+        }
+    }
+}
+
+impl<T: Recycle, E> MemoryPool<T, E> {
+    /// Constructor, must take intial size and maximum size.
+    /// The fallible creator closure is used to initialize the mem slots.
+    /// Slots are reset via `Recycle::recycle` before re-entering the pool;
+    /// use `MemoryPoolBuilder` for types that cannot implement `Recycle`
+    /// # Errors
+    /// This function returns the creator's error, wrapped in
+    /// `PoolError::CreationFailed`, if building one of the initial slots fails
+    /// # Panics
+    /// This function will panic if size > max
+    pub fn try_create_with(size: usize, max: usize, creator: TryCreateFn<T, E>) -> Result<MemoryPool<T, E>, PoolError<E>> {
+        let recycler: RecyclerFn<T> = Arc::new(|mem_slot: &mut T| mem_slot.recycle());
+        MemoryPool::build(size, max, creator, Some(recycler))
+    }
+}
+
+impl<T: Recycle + 'static> MemoryPool<T, ()> {
+    /// Constructor, must take intial size and maximum size.
+    /// The creator closure is used to initialize the mem slots
+    /// This is a thin wrapper around `try_create_with` for creators
+    /// that never fail
+    /// # Panics
+    /// This function will panic if size > max
+    pub fn create_with(size: usize, max: usize, creator: CreateFn<T>) -> MemoryPool<T, ()> {
+        MemoryPool::try_create_with(size, max, Box::new(move || Ok(creator()))).unwrap()
+    }
+}
+
+impl<T: 'static> MemoryPool<T, ()> {
+    /// Constructs a pool from the parts assembled by a `MemoryPoolBuilder`.
+    /// Unlike `create_with`, this does not require `T: Recycle`: slots are
+    /// reset with the builder's recycler closure, if any, or left
+    /// untouched otherwise
+    /// # Panics
+    /// This function will panic if size > max
+    pub(crate) fn from_builder(size: usize, max: usize, creator: CreateFn<T>, recycle: Option<BoxedRecycleFn<T>>) -> MemoryPool<T, ()> {
+        let creator: TryCreateFn<T, ()> = Box::new(move || Ok(creator()));
+        MemoryPool::build(size, max, creator, recycle.map(Arc::from)).unwrap()
     }
 }
 
@@ -121,7 +233,7 @@ mod tests {
     #[test]
     fn creation_test() {
         let mem = MemoryPool::create_with(5, 10, Box::new(|| { Vec::<f64>::with_capacity(20) }));
-        let _v1 = mem.get();
+        let _v1 = mem.get().unwrap();
         let _v2 = mem.try_get().unwrap();
     }
 
@@ -130,7 +242,7 @@ mod tests {
         let mem = MemoryPool::create_with(5, 10, Box::new(|| { Vec::<f64>::with_capacity(20) }));
         let mut vecs = vec![];
         for _ in 0..10 {
-            vecs.push(mem.get());
+            vecs.push(mem.get().unwrap());
         }
     }
 
@@ -141,7 +253,7 @@ mod tests {
         {
             let mut vecs = vec![];
             for _ in 0..10 {
-                vecs.push(mem.get());
+                vecs.push(mem.get().unwrap());
             }
         }
 
@@ -149,28 +261,82 @@ mod tests {
         {
             let mut vecs = vec![];
             for _ in 0..10 {
-                vecs.push(mem.get());
+                vecs.push(mem.get().unwrap());
             }
         }
     }
 
     #[test]
-    #[should_panic]
     fn too_many_elements_test() {
         let mem = MemoryPool::create_with(5, 10, Box::new(|| { Vec::<f64>::with_capacity(20) }));
         let mut vecs = vec![];
-        for _ in 0..11 {
-            vecs.push(mem.get());
+        for _ in 0..10 {
+            vecs.push(mem.get().unwrap());
+        }
+
+        match mem.get() {
+            Err(PoolError::Exhausted) => {}
+            _ => panic!("Expected PoolError::Exhausted"),
         }
     }
 
+    #[test]
+    fn exhausted_does_not_leak_size_test() {
+        let mem = MemoryPool::create_with(2, 2, Box::new(|| { Vec::<f64>::with_capacity(20) }));
+        let v1 = mem.get().unwrap();
+        let _v2 = mem.get().unwrap();
+
+        /// A refused allocation must not permanently inflate `size`
+        match mem.get() {
+            Err(PoolError::Exhausted) => {}
+            _ => panic!("Expected PoolError::Exhausted"),
+        }
+
+        /// Freeing up a slot (here via detach, which leaves a tombstone
+        /// instead of a slot) must let the pool create again
+        assert!(v1.detach().is_some());
+        assert!(mem.get().is_ok());
+    }
+
     #[test]
     fn too_many_elements_try_test() {
         let mem = MemoryPool::create_with(5, 10, Box::new(|| { Vec::<f64>::with_capacity(20) }));
         let mut vecs = vec![];
         for _ in 0..10 {
-            vecs.push(mem.get());
+            vecs.push(mem.get().unwrap());
         }
         assert!(mem.try_get().is_none());
     }
+
+    #[test]
+    fn attach_test() {
+        let mem = MemoryPool::create_with(0, 1, Box::new(|| { Vec::<f64>::with_capacity(20) }));
+        let mut external = Vec::<f64>::with_capacity(20);
+        external.push(1.0);
+
+        assert!(mem.attach(external).is_ok());
+
+        let recycled = mem.get().unwrap();
+        assert_eq!(recycled.len(), 0);
+        assert_eq!(recycled.capacity(), 20);
+    }
+
+    #[test]
+    fn attach_over_max_test() {
+        let mem = MemoryPool::create_with(1, 1, Box::new(|| { Vec::<f64>::with_capacity(20) }));
+
+        let rejected = mem.attach(Vec::<f64>::with_capacity(20));
+        assert!(rejected.is_err());
+    }
+
+    #[test]
+    fn try_create_with_failing_creator_test() {
+        let result: Result<MemoryPool<Vec<f64>, &'static str>, PoolError<&'static str>> =
+            MemoryPool::try_create_with(1, 5, Box::new(|| Err("mmap failed")));
+
+        match result {
+            Err(PoolError::CreationFailed("mmap failed")) => {}
+            _ => panic!("Expected PoolError::CreationFailed"),
+        }
+    }
 }
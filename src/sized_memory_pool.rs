@@ -0,0 +1,117 @@
+use std::sync::Arc;
+use std::sync::mpsc;
+use arc_recycled::{
+    ArcRecycled,
+    Recycle,
+};
+use memory_pool::MemoryPool;
+
+/// Boxed closure that creates an object of type T with a given capacity
+pub type SizedCreateFn<T> = Box<Fn(usize) -> T>;
+
+/// One capacity class within a `SizedMemoryPool`
+#[allow(missing_debug_implementations)]
+struct SizeClass<T> {
+    capacity: usize,
+    pool: MemoryPool<T, ()>,
+}
+
+/// A pool layered over several capacity classes, each backed by its own
+/// `MemoryPool`. Requests are served from the smallest class that can
+/// hold them, so callers can reuse objects of wildly different sizes
+/// without a single class either wasting memory or being too small
+#[allow(missing_debug_implementations)]
+pub struct SizedMemoryPool<T> {
+    classes: Vec<SizeClass<T>>,
+    overflow: Option<SizedCreateFn<T>>,
+}
+
+impl<T: Recycle + 'static> SizedMemoryPool<T> {
+    /// Constructor. `classes` is an ordered list of `(class_capacity, initial, max)`
+    /// tuples, from smallest to largest, describing each capacity class.
+    /// `creator` builds an object of the given capacity for any of them.
+    /// `overflow`, if given, is used to serve requests bigger than the
+    /// largest class instead of returning `None`
+    /// # Panics
+    /// This function will panic if `classes` is not sorted by strictly
+    /// increasing `class_capacity`
+    pub fn create_with(classes: &[(usize, usize, usize)], creator: SizedCreateFn<T>, overflow: Option<SizedCreateFn<T>>) -> SizedMemoryPool<T> {
+        let creator = Arc::new(creator);
+        let mut built = Vec::with_capacity(classes.len());
+        let mut previous_capacity = 0;
+
+        for (index, &(capacity, initial, max)) in classes.iter().enumerate() {
+            assert!(index == 0 || capacity > previous_capacity, "classes must be sorted by strictly increasing class_capacity");
+            previous_capacity = capacity;
+
+            let creator = creator.clone();
+            let pool = MemoryPool::create_with(initial, max, Box::new(move || creator(capacity)));
+            built.push(SizeClass { capacity, pool });
+        }
+
+        SizedMemoryPool {
+            classes: built,
+            overflow,
+        }
+    }
+
+    /// Returns a memory slot able to hold at least `min_capacity`, served
+    /// from the smallest class whose `class_capacity >= min_capacity`.
+    /// Falls back to the overflow creator, if any, when `min_capacity`
+    /// exceeds every class; returns `None` when there is no overflow
+    /// creator or the matched class is exhausted
+    pub fn get(&self, min_capacity: usize) -> Option<ArcRecycled<T>> {
+        match self.classes.iter().find(|class| class.capacity >= min_capacity) {
+            Some(class) => class.pool.try_get(),
+            None => self.overflow.as_ref().map(|creator| {
+                /// Overflow objects are not pooled: the channel's receiver
+                /// is dropped immediately, so recycling on drop is a no-op
+                let (tx, _rx) = mpsc::channel();
+                ArcRecycled::new(creator(min_capacity), tx)
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn smallest_fitting_class_test() {
+        let mem = SizedMemoryPool::create_with(
+            &[(16, 1, 4), (64, 1, 4), (256, 1, 4)],
+            Box::new(|capacity| Vec::<u8>::with_capacity(capacity)),
+            None,
+        );
+
+        let small = mem.get(10).unwrap();
+        assert!(small.capacity() >= 10 && small.capacity() < 64);
+
+        let medium = mem.get(40).unwrap();
+        assert!(medium.capacity() >= 40 && medium.capacity() < 256);
+    }
+
+    #[test]
+    fn overflow_test() {
+        let mem = SizedMemoryPool::create_with(
+            &[(16, 1, 4)],
+            Box::new(|capacity| Vec::<u8>::with_capacity(capacity)),
+            Some(Box::new(|capacity| Vec::<u8>::with_capacity(capacity))),
+        );
+
+        let huge = mem.get(1000).unwrap();
+        assert!(huge.capacity() >= 1000);
+    }
+
+    #[test]
+    fn no_overflow_test() {
+        let mem = SizedMemoryPool::create_with(
+            &[(16, 1, 4)],
+            Box::new(|capacity| Vec::<u8>::with_capacity(capacity)),
+            None,
+        );
+
+        assert!(mem.get(1000).is_none());
+    }
+}